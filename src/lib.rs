@@ -1,32 +1,737 @@
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 
 use core::sync::atomic::{AtomicU8,Ordering};
-use core::cell::Cell;
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ptr;
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for super::MultiCore {}
+    impl Sealed for super::SingleCore {}
+}
+
+/// Selects the memory-ordering strategy used by a [`ShortQueue`].
+///
+/// This is a sealed trait; [`MultiCore`] and [`SingleCore`] are the only
+/// implementations.
+pub trait CoreKind: sealed::Sealed {
+    /// `true` when the producer and consumer are guaranteed to never run
+    /// truly concurrently (e.g. both run to completion inside interrupt
+    /// handlers on a single core).
+    const SINGLE_CORE: bool;
+
+    #[inline]
+    fn index_load_ordering() -> Ordering {
+        if Self::SINGLE_CORE { Ordering::Relaxed } else { Ordering::Acquire }
+    }
+
+    #[inline]
+    fn index_store_ordering() -> Ordering {
+        if Self::SINGLE_CORE { Ordering::Relaxed } else { Ordering::Release }
+    }
+}
+
+/// Producer and consumer may run concurrently on different cores; the
+/// index that hands off ownership of `buf` is loaded/stored with
+/// `Acquire`/`Release`.  This is the default and is always correct.
+pub struct MultiCore;
+
+/// Producer and consumer never run truly concurrently (e.g. they are both
+/// driven from interrupt handlers on a single core), so the hand-off
+/// index can be loaded/stored with `Relaxed` ordering: the CPU's own
+/// program order already guarantees the write to `buf` happens-before the
+/// index update.
+pub struct SingleCore;
+
+impl CoreKind for MultiCore {
+    const SINGLE_CORE: bool = false;
+}
+
+impl CoreKind for SingleCore {
+    const SINGLE_CORE: bool = true;
+}
+
+#[inline]
+fn increment<const N: usize>( p: u8 ) -> u8 {
+    // `N as u8` truncates to `0` when `N == 256` (a valid, asserted size
+    // -- see `ShortQueue::new`), which would divide by zero. Do the
+    // wraparound arithmetic in `u16`, where `N` always fits, and only
+    // narrow the result back to `u8` at the end.
+    ( (p as u16 + 1) % (N as u16) ) as u8
+}
+
+#[inline]
+fn queue_len<const N: usize>( head: u8, tail: u8 ) -> usize {
+    // Same `N == 256` truncation hazard as `increment`: do the
+    // subtraction/modulo in `u16`.
+    usize::from( ( u16::from(tail) + (N as u16) - u16::from(head) ) % (N as u16) )
+}
+
+// `ShortQueue` and `PaddedShortQueue` differ only in where `head`/`tail`
+// live (packed together vs. each on its own cache line); the actual SPSC
+// ring-buffer algorithm -- and the ordering rationale behind it -- lives
+// here exactly once, and both queue layouts borrow into it through
+// `Core`.
+struct Core<'q, T, const N: usize, C: CoreKind> {
+    head: &'q AtomicU8,
+    tail: &'q AtomicU8,
+    buf: &'q [UnsafeCell<MaybeUninit<T>>; N],
+    _core: PhantomData<C>
+}
+
+impl<'q, T, const N: usize, C: CoreKind> Core<'q, T, N, C> {
+
+    fn len( &self ) -> usize {
+        let head = self.head.load( Ordering::Relaxed );
+        let tail = self.tail.load( Ordering::Relaxed );
+
+        queue_len::<N>( head, tail )
+    }
+
+    fn push( &self, v: T ) -> bool {
+        // The tail is owned by `push`.  So the load is `Relaxed` since
+        // this context's version is up to date.
+        let tail = self.tail.load( Ordering::Relaxed );
+
+        let next_tail = increment::<N>( tail );
+
+        // The queue is full if the followup write location is `head`.  The
+        // load uses `C::index_load_ordering()`: `Acquire` for `MultiCore`
+        // since it pairs with the consumer's `Release` store, or `Relaxed`
+        // for `SingleCore` where program order already orders the two.
+        if next_tail == self.head.load( C::index_load_ordering() ) {
+            return false;
+        }
+
+        unsafe {
+            let slot = self.buf.get_unchecked( usize::from( tail ) ).get();
+            ptr::write( slot.cast::<T>(), v );
+        }
+
+        // The store uses `C::index_store_ordering()` so that, for
+        // `MultiCore`, the memory write to buf above is guaranteed to be
+        // completed and broadcast to memory before `tail` is updated.
+        self.tail.store( next_tail, C::index_store_ordering() );
+
+        true
+    }
+
+    fn pop( &self ) -> Option<T> {
+
+        // The head is owned by `pop`.  So the load is `Relaxed` since
+        // this context's version is up to date.
+        let head = self.head.load( Ordering::Relaxed );
+
+        // The queue is empty if `head` = `tail`. The load uses
+        // `C::index_load_ordering()` since writes to `tail` by the
+        // producer use `C::index_store_ordering()`.
+        if head == self.tail.load( C::index_load_ordering() ) {
+            return None;
+        }
+
+        let next_head = increment::<N>( head );
+
+        let rv = unsafe {
+            let slot = self.buf.get_unchecked( usize::from( head ) ).get();
+            ptr::read( slot.cast::<T>() )
+        };
+
+        // The store uses `C::index_store_ordering()` to ensure that, for
+        // `MultiCore`, the memory read from `buf` happens before the value
+        // of `head` is updated.  Otherwise the producer might overwrite
+        // the value we are about to read.
+
+        self.head.store( next_head, C::index_store_ordering() );
+
+        Some( rv )
+    }
+
+    fn drain( &self ) {
+        self.head.store( self.tail.load( C::index_load_ordering() ), C::index_store_ordering() );
+    }
+
+    fn is_empty( &self ) -> bool {
+        self.head.load( Ordering::Relaxed ) == self.tail.load( Ordering::Relaxed )
+    }
+
+    fn is_full( &self ) -> bool {
+        increment::<N>( self.tail.load( Ordering::Relaxed) ) == self.head.load( Ordering::Relaxed )
+    }
+}
+
+impl<'q, const N: usize, C: CoreKind> Core<'q, u8, N, C> {
+
+    // A raw pointer to the byte stored at `idx`.  `buf` is an array of
+    // `UnsafeCell<MaybeUninit<u8>>`, which has the same layout as `u8`,
+    // so the cast below is sound; the caller is responsible for only
+    // touching indices that are actually initialized.
+    #[inline]
+    unsafe fn slot_ptr( &self, idx: usize ) -> *mut u8 {
+        self.buf.get_unchecked( idx ).get().cast::<u8>()
+    }
+
+    fn push_slice( &self, src: &[u8] ) -> usize {
+        // The tail is owned by the producer, so `Relaxed` is enough for it;
+        // `head` is loaded with `C::index_load_ordering()` up front,
+        // mirroring `push`.
+        let head = self.head.load( C::index_load_ordering() );
+        let tail = self.tail.load( Ordering::Relaxed );
+
+        let len = queue_len::<N>( head, tail );
+        let n = src.len().min( (N - 1) - len );
+
+        if n == 0 {
+            return 0;
+        }
+
+        let tail_idx = usize::from( tail );
+        let first = n.min( N - tail_idx );
+
+        unsafe {
+            let run = core::slice::from_raw_parts_mut( self.slot_ptr( tail_idx ), first );
+            run.copy_from_slice( &src[..first] );
+        }
+
+        if first < n {
+            unsafe {
+                let run = core::slice::from_raw_parts_mut( self.slot_ptr( 0 ), n - first );
+                run.copy_from_slice( &src[first..n] );
+            }
+        }
+
+        // A single store (with `C::index_store_ordering()`) after both
+        // memcpys is enough: it still happens-after every byte write
+        // above, so a `MultiCore` consumer can't observe `tail` moving
+        // before the bytes it covers are in place.
+        self.tail.store( ((tail_idx + n) % N) as u8, C::index_store_ordering() );
+
+        n
+    }
+
+    fn pop_slice( &self, dst: &mut [u8] ) -> usize {
+        // The head is owned by the consumer, so `Relaxed` is enough for it;
+        // `tail` is loaded with `C::index_load_ordering()` up front,
+        // mirroring `pop`.
+        let head = self.head.load( Ordering::Relaxed );
+        let tail = self.tail.load( C::index_load_ordering() );
+
+        let len = queue_len::<N>( head, tail );
+        let n = dst.len().min( len );
+
+        if n == 0 {
+            return 0;
+        }
+
+        let head_idx = usize::from( head );
+        let first = n.min( N - head_idx );
+
+        unsafe {
+            let run = core::slice::from_raw_parts( self.slot_ptr( head_idx ), first );
+            dst[..first].copy_from_slice( run );
+        }
+
+        if first < n {
+            unsafe {
+                let run = core::slice::from_raw_parts( self.slot_ptr( 0 ), n - first );
+                dst[first..n].copy_from_slice( run );
+            }
+        }
+
+        self.head.store( ((head_idx + n) % N) as u8, C::index_store_ordering() );
+
+        n
+    }
+
+    /// Returns the front byte without consuming it: `head` is only read,
+    /// never stored, so a following `pop`/`pop_slice` still returns the
+    /// same byte.
+    fn peek( &self ) -> Option<u8> {
+        let head = self.head.load( Ordering::Relaxed );
+        let tail = self.tail.load( C::index_load_ordering() );
+
+        if head == tail {
+            return None;
+        }
+
+        Some( unsafe { ptr::read( self.slot_ptr( usize::from( head ) ) ) } )
+    }
+
+    /// Copies up to `dst.len()` queued bytes into `dst` without consuming
+    /// them, returning the number copied. Like [`peek`](Self::peek), this
+    /// only loads `head`/`tail`; it never stores `head`.
+    fn peek_slice( &self, dst: &mut [u8] ) -> usize {
+        let head = self.head.load( Ordering::Relaxed );
+        let tail = self.tail.load( C::index_load_ordering() );
+
+        let len = queue_len::<N>( head, tail );
+        let n = dst.len().min( len );
+
+        if n == 0 {
+            return 0;
+        }
+
+        let head_idx = usize::from( head );
+        let first = n.min( N - head_idx );
+
+        unsafe {
+            let run = core::slice::from_raw_parts( self.slot_ptr( head_idx ), first );
+            dst[..first].copy_from_slice( run );
+        }
+
+        if first < n {
+            unsafe {
+                let run = core::slice::from_raw_parts( self.slot_ptr( 0 ), n - first );
+                dst[first..n].copy_from_slice( run );
+            }
+        }
+
+        n
+    }
+}
+
+// Shared by every queue layout's `Drop` impl: pop-and-drop every slot
+// still holding an initialized value between `head` and `tail`.
+fn drop_remaining<T, const N: usize>( mut head: u8, tail: u8, buf: &mut [UnsafeCell<MaybeUninit<T>>; N] ) {
+    while head != tail {
+        unsafe {
+            let slot = buf.get_unchecked( usize::from( head ) ).get();
+            ptr::drop_in_place( slot.cast::<T>() );
+        }
+        head = increment::<N>( head );
+    }
+}
+
+pub struct ShortQueue<T, const N: usize, C: CoreKind = MultiCore> {
+
+    head: AtomicU8,
+    tail: AtomicU8,
+    buf: [UnsafeCell<MaybeUninit<T>>; N],
+    _core: PhantomData<C>
+}
+
+impl<T, const N: usize, C: CoreKind> ShortQueue<T, N, C> {
+
+    pub const fn new() -> Self {
+        assert!( N>0 );
+        assert!( N<= 256 );
+
+        ShortQueue {
+            head: AtomicU8::new(0),
+            tail: AtomicU8::new(0),
+            buf: [const { UnsafeCell::new(MaybeUninit::uninit()) }; N],
+            _core: PhantomData
+        }
+    }
+
+    pub const fn capacity( &self ) -> usize {
+        N - 1
+    }
+
+    #[inline]
+    fn core( &self ) -> Core<'_, T, N, C> {
+        Core { head: &self.head, tail: &self.tail, buf: &self.buf, _core: PhantomData }
+    }
+
+    pub fn len( &self ) -> usize {
+        self.core().len()
+    }
+
+    #[inline]
+    pub fn push( &mut self, v: T ) -> bool {
+        self.push_inner( v )
+    }
+
+    fn push_inner( &self, v: T ) -> bool {
+        self.core().push( v )
+    }
+
+    pub fn pop( &mut self ) -> Option<T> {
+        self.pop_inner()
+    }
+
+    fn pop_inner( &self ) -> Option<T> {
+        self.core().pop()
+    }
+
+    pub fn drain( &self ) {
+        self.core().drain()
+    }
+
+    pub fn is_empty( &self ) -> bool {
+        self.core().is_empty()
+    }
+
+    pub fn is_full( &self ) -> bool {
+        self.core().is_full()
+    }
+
+    pub fn split( &mut self ) -> (Producer<'_,T,N,C>, Consumer<'_,T,N,C>) {
+        let p = Producer { core: self };
+        let c = Consumer { core: self };
+        ( p, c )
+    }
+
+}
+
+impl<const N: usize, C: CoreKind> ShortQueue<u8, N, C> {
+
+    #[inline]
+    pub fn push_slice( &mut self, src: &[u8] ) -> usize {
+        self.push_slice_inner( src )
+    }
+
+    fn push_slice_inner( &self, src: &[u8] ) -> usize {
+        self.core().push_slice( src )
+    }
+
+    #[inline]
+    pub fn pop_slice( &mut self, dst: &mut [u8] ) -> usize {
+        self.pop_slice_inner( dst )
+    }
+
+    fn pop_slice_inner( &self, dst: &mut [u8] ) -> usize {
+        self.core().pop_slice( dst )
+    }
+
+    pub fn peek( &self ) -> Option<u8> {
+        self.peek_inner()
+    }
+
+    fn peek_inner( &self ) -> Option<u8> {
+        self.core().peek()
+    }
+
+    pub fn peek_slice( &self, dst: &mut [u8] ) -> usize {
+        self.peek_slice_inner( dst )
+    }
+
+    fn peek_slice_inner( &self, dst: &mut [u8] ) -> usize {
+        self.core().peek_slice( dst )
+    }
+}
+
+impl<T, const N: usize, C: CoreKind> Drop for ShortQueue<T, N, C> {
+    fn drop( &mut self ) {
+        // `&mut self` means no producer/consumer can be live, so plain
+        // loads of the indices (via `get_mut`) are enough; drop every
+        // slot still holding an initialized value between `head` and `tail`.
+        drop_remaining( *self.head.get_mut(), *self.tail.get_mut(), &mut self.buf );
+    }
+}
+
+
+pub struct Producer<'a, T, const N: usize, C: CoreKind = MultiCore> {
+    core: &'a ShortQueue<T, N, C>,
+}
+
+impl<'a, T, const N: usize, C: CoreKind> Producer<'a, T, N, C> {
+
+    #[inline]
+    pub fn push( &mut self, v: T ) -> bool {
+        self.core.push_inner( v )
+    }
+
+    #[inline]
+    pub fn is_empty( &self ) -> bool {
+        self.core.is_empty()
+    }
+
+    #[inline]
+    pub fn is_full( &self ) -> bool {
+        self.core.is_full()
+    }
+}
+
+impl<'a, const N: usize, C: CoreKind> Producer<'a, u8, N, C> {
+
+    #[inline]
+    pub fn push_slice( &mut self, src: &[u8] ) -> usize {
+        self.core.push_slice_inner( src )
+    }
+}
+
+pub struct Consumer<'a, T, const N: usize, C: CoreKind = MultiCore> {
+    core: &'a ShortQueue<T, N, C>
+}
+
+impl<'a, T, const N: usize, C: CoreKind> Consumer<'a, T, N, C> {
+
+    #[inline]
+    pub fn pop( &mut self ) -> Option<T> {
+        self.core.pop_inner()
+    }
+
+    #[inline]
+    pub fn drain( &mut self ) {
+        self.core.drain()
+    }
+
+    #[inline]
+    pub fn is_empty( &self ) -> bool {
+        self.core.is_empty()
+    }
+
+    #[inline]
+    pub fn is_full( &self ) -> bool {
+        self.core.is_full()
+    }
+}
+
+impl<'a, const N: usize, C: CoreKind> Consumer<'a, u8, N, C> {
+
+    #[inline]
+    pub fn pop_slice( &mut self, dst: &mut [u8] ) -> usize {
+        self.core.pop_slice_inner( dst )
+    }
+
+    #[inline]
+    pub fn peek( &self ) -> Option<u8> {
+        self.core.peek_inner()
+    }
+
+    #[inline]
+    pub fn peek_slice( &self, dst: &mut [u8] ) -> usize {
+        self.core.peek_slice_inner( dst )
+    }
+}
+
+// `ShortQueue` is fundamentally a byte pipe, so `Producer`/`Consumer` plug
+// into the standard I/O traits for a UART-driver-to-parser hookup. These
+// impls are feature-gated so the core crate stays `no_std` with no deps.
+
+#[cfg(feature = "embedded-io")]
+impl<'a, const N: usize, C: CoreKind> embedded_io::ErrorType for Producer<'a, u8, N, C> {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<'a, const N: usize, C: CoreKind> embedded_io::Write for Producer<'a, u8, N, C> {
+    #[inline]
+    fn write( &mut self, buf: &[u8] ) -> Result<usize, Self::Error> {
+        Ok( self.push_slice( buf ) )
+    }
+
+    #[inline]
+    fn flush( &mut self ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<'a, const N: usize, C: CoreKind> embedded_io::ErrorType for Consumer<'a, u8, N, C> {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<'a, const N: usize, C: CoreKind> embedded_io::Read for Consumer<'a, u8, N, C> {
+    #[inline]
+    fn read( &mut self, buf: &mut [u8] ) -> Result<usize, Self::Error> {
+        // A `0` return here means "queue is empty right now", not "closed" --
+        // there is no end-of-stream. Don't reach for `ReadExactError`-based
+        // helpers (e.g. `read_exact`) on this `Read`; they treat a `0`
+        // return as EOF and bail out instead of retrying.
+        Ok( self.pop_slice( buf ) )
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, const N: usize, C: CoreKind> std::io::Write for Producer<'a, u8, N, C> {
+    #[inline]
+    fn write( &mut self, buf: &[u8] ) -> std::io::Result<usize> {
+        // A `0` return with a non-empty `buf` is the standard signal for
+        // "no room right now"; `write_all` already turns that into
+        // `ErrorKind::WriteZero` for us.
+        Ok( self.push_slice( buf ) )
+    }
+
+    #[inline]
+    fn flush( &mut self ) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::sync::Arc;
+
+/// The owned, producer half of a queue split with [`ShortQueue::split_arc`].
+///
+/// Unlike [`Producer`], this holds its own `Arc` to the queue rather than
+/// borrowing it, so it can be handed to another thread.
+#[cfg(feature = "alloc")]
+pub struct OwnedProducer<T, const N: usize, C: CoreKind = MultiCore> {
+    core: Arc<ShortQueue<T, N, C>>,
+}
+
+/// The owned, consumer half of a queue split with [`ShortQueue::split_arc`].
+///
+/// Unlike [`Consumer`], this holds its own `Arc` to the queue rather than
+/// borrowing it, so it can be handed to another thread.
+#[cfg(feature = "alloc")]
+pub struct OwnedConsumer<T, const N: usize, C: CoreKind = MultiCore> {
+    core: Arc<ShortQueue<T, N, C>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<T, const N: usize, C: CoreKind> ShortQueue<T, N, C> {
+
+    /// Like [`split`](ShortQueue::split), but hands out `Arc`-backed,
+    /// `Send` endpoints that own their share of the queue instead of
+    /// borrowing it, so the producer and consumer can live on different
+    /// threads.
+    pub fn split_arc( self ) -> (OwnedProducer<T, N, C>, OwnedConsumer<T, N, C>) {
+        let core = Arc::new( self );
+
+        ( OwnedProducer { core: core.clone() }, OwnedConsumer { core } )
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, const N: usize, C: CoreKind> OwnedProducer<T, N, C> {
+
+    #[inline]
+    pub fn push( &mut self, v: T ) -> bool {
+        self.core.push_inner( v )
+    }
 
-pub struct ShortQueue<const N: usize> {
+    #[inline]
+    pub fn is_empty( &self ) -> bool {
+        self.core.is_empty()
+    }
 
-    head: AtomicU8,
-    tail: AtomicU8,
-    buf: [Cell<u8>; N]
+    #[inline]
+    pub fn is_full( &self ) -> bool {
+        self.core.is_full()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, const N: usize, C: CoreKind> OwnedConsumer<T, N, C> {
+
+    #[inline]
+    pub fn pop( &mut self ) -> Option<T> {
+        self.core.pop_inner()
+    }
+
+    #[inline]
+    pub fn drain( &mut self ) {
+        self.core.drain()
+    }
+
+    #[inline]
+    pub fn is_empty( &self ) -> bool {
+        self.core.is_empty()
+    }
+
+    #[inline]
+    pub fn is_full( &self ) -> bool {
+        self.core.is_full()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<const N: usize, C: CoreKind> OwnedProducer<u8, N, C> {
+
+    #[inline]
+    pub fn push_slice( &mut self, src: &[u8] ) -> usize {
+        self.core.push_slice_inner( src )
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<const N: usize, C: CoreKind> OwnedConsumer<u8, N, C> {
+
+    #[inline]
+    pub fn pop_slice( &mut self, dst: &mut [u8] ) -> usize {
+        self.core.pop_slice_inner( dst )
+    }
+
+    #[inline]
+    pub fn peek( &self ) -> Option<u8> {
+        self.core.peek_inner()
+    }
+
+    #[inline]
+    pub fn peek_slice( &self, dst: &mut [u8] ) -> usize {
+        self.core.peek_slice_inner( dst )
+    }
+}
+
+// SAFETY: `split_arc` consumes the queue and hands out exactly one
+// `OwnedProducer` and one `OwnedConsumer`, so the two `Arc` clones are
+// used by at most one producer and one consumer respectively -- the
+// single-producer/single-consumer pattern `push_inner`/`pop_inner` are
+// already built around. `T: Send` is required because a value pushed on
+// one thread is dropped (on `pop`, or on the queue's `Drop`) on another.
+#[cfg(feature = "alloc")]
+unsafe impl<T: Send, const N: usize, C: CoreKind> Send for OwnedProducer<T, N, C> {}
+#[cfg(feature = "alloc")]
+unsafe impl<T: Send, const N: usize, C: CoreKind> Send for OwnedConsumer<T, N, C> {}
+
+/// Pads `T` out to its own cache line, so that two `CachePadded` fields
+/// sitting next to each other in a struct never share a cache line.
+///
+/// This is what [`PaddedShortQueue`] uses to keep `head` and `tail` apart:
+/// without it, a producer's `push` and a consumer's `pop` running on
+/// different cores would each invalidate the other's copy of the same
+/// line even though they touch different atomics.
+#[repr(align(64))]
+pub struct CachePadded<T>( T );
+
+impl<T> CachePadded<T> {
+    pub const fn new( v: T ) -> Self {
+        CachePadded( v )
+    }
 }
 
-impl<const N: usize> ShortQueue<N> {
+impl<T> core::ops::Deref for CachePadded<T> {
+    type Target = T;
 
-    const INIT:Cell<u8> = Cell::new(0);
+    #[inline]
+    fn deref( &self ) -> &T {
+        &self.0
+    }
+}
 
+impl<T> core::ops::DerefMut for CachePadded<T> {
     #[inline]
-    fn increment( p: u8 ) -> u8 {
-        p.wrapping_add(1) % (N as u8)
+    fn deref_mut( &mut self ) -> &mut T {
+        &mut self.0
     }
+}
+
+/// A [`ShortQueue`] with `head` and `tail` each pinned to their own cache
+/// line via [`CachePadded`], for producers and consumers that run on
+/// different cores and would otherwise serialize on false sharing.
+///
+/// `no_std` single-core users who don't care about false sharing (and
+/// would rather keep the compact layout) should use [`ShortQueue`]
+/// instead. Otherwise this has the same API as [`ShortQueue`], including
+/// the `u8`-specialized bulk/peek methods and, under the same feature
+/// gates, the `embedded-io`/`std::io` impls.
+pub struct PaddedShortQueue<T, const N: usize, C: CoreKind = MultiCore> {
+
+    head: CachePadded<AtomicU8>,
+    tail: CachePadded<AtomicU8>,
+    buf: [UnsafeCell<MaybeUninit<T>>; N],
+    _core: PhantomData<C>
+}
+
+impl<T, const N: usize, C: CoreKind> PaddedShortQueue<T, N, C> {
 
     pub const fn new() -> Self {
         assert!( N>0 );
         assert!( N<= 256 );
 
-        ShortQueue {
-            head: AtomicU8::new(0),
-            tail: AtomicU8::new(0),
-            buf: [Self::INIT; N]
+        PaddedShortQueue {
+            head: CachePadded::new( AtomicU8::new(0) ),
+            tail: CachePadded::new( AtomicU8::new(0) ),
+            buf: [const { UnsafeCell::new(MaybeUninit::uninit()) }; N],
+            _core: PhantomData
         }
     }
 
@@ -34,99 +739,104 @@ impl<const N: usize> ShortQueue<N> {
         N - 1
     }
 
-    pub fn len( &self ) -> usize {
-        let head = self.head.load( Ordering::Relaxed );
-        let tail = self.tail.load( Ordering::Relaxed );
+    #[inline]
+    fn core( &self ) -> Core<'_, T, N, C> {
+        Core { head: &self.head, tail: &self.tail, buf: &self.buf, _core: PhantomData }
+    }
 
-        usize::from( tail.wrapping_sub( head ).wrapping_add( N as u8 ) % (N as u8) )
+    pub fn len( &self ) -> usize {
+        self.core().len()
     }
 
     #[inline]
-    pub fn push( &mut self, v: u8 ) -> bool {
+    pub fn push( &mut self, v: T ) -> bool {
         self.push_inner( v )
     }
 
-    fn push_inner( &self, v: u8 ) -> bool {
-        // The tail is owned by `push`.  So the load is `Relaxed` since
-        // this context's version is up to date.
-        let tail = self.tail.load( Ordering::Relaxed );
-
-        let next_tail = Self::increment( tail );
-
-        // The queue is full if the followup write location is `head`.  The
-        // load is `Acquire` because it is `Release` for the consumer 
-        if next_tail == self.head.load( Ordering::Acquire ) {
-            return false;
-        }
-
-        unsafe { *self.buf.get_unchecked_mut(usize::from( tail )) = v }
+    fn push_inner( &self, v: T ) -> bool {
+        self.core().push( v )
+    }
 
-        // The store is `Release` so that the memory write to buf above is guaranteed
-        // to be completed and broadcast to memory before `tail` is updated.
-        self.tail.store( next_tail, Ordering::Release );
+    pub fn pop( &mut self ) -> Option<T> {
+        self.pop_inner()
+    }
 
-        true
+    fn pop_inner( &self ) -> Option<T> {
+        self.core().pop()
     }
 
-    pub fn pop( &mut self ) -> Option<u8> {
-        self.pop_inner()
+    pub fn drain( &self ) {
+        self.core().drain()
     }
 
-    fn pop_inner( &self ) -> Option<u8> {
+    pub fn is_empty( &self ) -> bool {
+        self.core().is_empty()
+    }
 
-        // The head is owned by `pop`.  So the load is `Relaxed` since
-        // this context's version is up to date.
-        let head = self.head.load( Ordering::Relaxed );
+    pub fn is_full( &self ) -> bool {
+        self.core().is_full()
+    }
 
-        // The queue is empty if `head` = `tail`. The load is
-        // `Acquire` since writes to `tail` by the producer are `Release`.
-        if head == self.tail.load( Ordering::Acquire ) {
-            return None;
-        }
+    pub fn split( &mut self ) -> (PaddedProducer<'_,T,N,C>, PaddedConsumer<'_,T,N,C>) {
+        let p = PaddedProducer { core: self };
+        let c = PaddedConsumer { core: self };
+        ( p, c )
+    }
 
-        let next_head = Self::increment( head );
+}
 
-        let rv = self.buf[ usize::from( head )].get();
+impl<const N: usize, C: CoreKind> PaddedShortQueue<u8, N, C> {
 
-        // The store is `Release` to ensure that the memory read from `buf`
-        // happens before the value of `head` is updated.  Otherwise 
-        // the producer might overwrite the value we are about to read.
+    #[inline]
+    pub fn push_slice( &mut self, src: &[u8] ) -> usize {
+        self.push_slice_inner( src )
+    }
 
-        self.head.store( next_head, Ordering::Release );
+    fn push_slice_inner( &self, src: &[u8] ) -> usize {
+        self.core().push_slice( src )
+    }
 
-        Some( rv )
+    #[inline]
+    pub fn pop_slice( &mut self, dst: &mut [u8] ) -> usize {
+        self.pop_slice_inner( dst )
     }
 
-    pub fn drain( &self ) {
-        self.head.store( self.tail.load(Ordering::Acquire), Ordering::Release );
+    fn pop_slice_inner( &self, dst: &mut [u8] ) -> usize {
+        self.core().pop_slice( dst )
     }
 
-    pub fn is_empty( &self ) -> bool {
-        self.head.load( Ordering::Relaxed ) == self.tail.load( Ordering::Relaxed )
+    pub fn peek( &self ) -> Option<u8> {
+        self.peek_inner()
     }
 
-    pub fn is_full( &self ) -> bool {
-        Self::increment( self.tail.load( Ordering::Relaxed) ) == self.head.load( Ordering::Relaxed )
+    fn peek_inner( &self ) -> Option<u8> {
+        self.core().peek()
     }
 
-    pub fn split( &mut self ) -> (Producer<'_,N>, Consumer<'_,N>) {
-        let p = Producer { core: self };
-        let c = Consumer { core: self };
-        ( p, c )
+    pub fn peek_slice( &self, dst: &mut [u8] ) -> usize {
+        self.peek_slice_inner( dst )
     }
 
+    fn peek_slice_inner( &self, dst: &mut [u8] ) -> usize {
+        self.core().peek_slice( dst )
+    }
 }
 
+impl<T, const N: usize, C: CoreKind> Drop for PaddedShortQueue<T, N, C> {
+    fn drop( &mut self ) {
+        drop_remaining( *self.head.get_mut(), *self.tail.get_mut(), &mut self.buf );
+    }
+}
 
-pub struct Producer<'a, const N: usize> {
-    core: &'a ShortQueue<N>,
+pub struct PaddedProducer<'a, T, const N: usize, C: CoreKind = MultiCore> {
+    core: &'a PaddedShortQueue<T, N, C>,
 }
 
-impl<'a, const N: usize> Producer<'a, N> {
+impl<'a, T, const N: usize, C: CoreKind> PaddedProducer<'a, T, N, C> {
 
     #[inline]
-    pub fn push( &mut self, b:u8 ) -> bool {
-        self.core.push_inner( b )
+    pub fn push( &mut self, v: T ) -> bool {
+        self.core.push_inner( v )
     }
 
     #[inline]
@@ -140,14 +850,22 @@ impl<'a, const N: usize> Producer<'a, N> {
     }
 }
 
-pub struct Consumer<'a, const N: usize> {
-    core: &'a ShortQueue<N>
+impl<'a, const N: usize, C: CoreKind> PaddedProducer<'a, u8, N, C> {
+
+    #[inline]
+    pub fn push_slice( &mut self, src: &[u8] ) -> usize {
+        self.core.push_slice_inner( src )
+    }
+}
+
+pub struct PaddedConsumer<'a, T, const N: usize, C: CoreKind = MultiCore> {
+    core: &'a PaddedShortQueue<T, N, C>
 }
 
-impl<'a, const N: usize> Consumer<'a, N> {
+impl<'a, T, const N: usize, C: CoreKind> PaddedConsumer<'a, T, N, C> {
 
     #[inline]
-    pub fn pop( &mut self ) -> Option<u8> {
+    pub fn pop( &mut self ) -> Option<T> {
         self.core.pop_inner()
     }
 
@@ -167,6 +885,72 @@ impl<'a, const N: usize> Consumer<'a, N> {
     }
 }
 
+impl<'a, const N: usize, C: CoreKind> PaddedConsumer<'a, u8, N, C> {
+
+    #[inline]
+    pub fn pop_slice( &mut self, dst: &mut [u8] ) -> usize {
+        self.core.pop_slice_inner( dst )
+    }
+
+    #[inline]
+    pub fn peek( &self ) -> Option<u8> {
+        self.core.peek_inner()
+    }
+
+    #[inline]
+    pub fn peek_slice( &self, dst: &mut [u8] ) -> usize {
+        self.core.peek_slice_inner( dst )
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<'a, const N: usize, C: CoreKind> embedded_io::ErrorType for PaddedProducer<'a, u8, N, C> {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<'a, const N: usize, C: CoreKind> embedded_io::Write for PaddedProducer<'a, u8, N, C> {
+    #[inline]
+    fn write( &mut self, buf: &[u8] ) -> Result<usize, Self::Error> {
+        Ok( self.push_slice( buf ) )
+    }
+
+    #[inline]
+    fn flush( &mut self ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<'a, const N: usize, C: CoreKind> embedded_io::ErrorType for PaddedConsumer<'a, u8, N, C> {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<'a, const N: usize, C: CoreKind> embedded_io::Read for PaddedConsumer<'a, u8, N, C> {
+    #[inline]
+    fn read( &mut self, buf: &mut [u8] ) -> Result<usize, Self::Error> {
+        // A `0` return here means "queue is empty right now", not "closed" --
+        // there is no end-of-stream. Don't reach for `ReadExactError`-based
+        // helpers (e.g. `read_exact`) on this `Read`; they treat a `0`
+        // return as EOF and bail out instead of retrying.
+        Ok( self.pop_slice( buf ) )
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, const N: usize, C: CoreKind> std::io::Write for PaddedProducer<'a, u8, N, C> {
+    #[inline]
+    fn write( &mut self, buf: &[u8] ) -> std::io::Result<usize> {
+        Ok( self.push_slice( buf ) )
+    }
+
+    #[inline]
+    fn flush( &mut self ) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -174,7 +958,7 @@ mod tests {
 
     #[test]
     fn basics() {
-        let q = ShortQueue::<8>::new();
+        let q = ShortQueue::<u8, 8>::new();
 
         assert_eq!( q.len(), 0 );
         assert_eq!( q.is_empty(), true );
@@ -184,7 +968,7 @@ mod tests {
 
     #[test]
     fn push() {
-        let mut q = ShortQueue::<8>::new();
+        let mut q = ShortQueue::<u8, 8>::new();
 
         for k in 0..7 {
             assert_eq!( q.push( k ), true );
@@ -198,7 +982,7 @@ mod tests {
     fn pop() {
         const QSIZE: u8 = 11;
 
-        let mut q = ShortQueue::<{QSIZE as usize}>::new();
+        let mut q = ShortQueue::<u8, {QSIZE as usize}>::new();
 
         for k in 0..QSIZE-1 {
             assert_eq!( q.push( k ), true );
@@ -216,7 +1000,7 @@ mod tests {
     fn wrap() {
         const QSIZE: u8 = 6;
 
-        let mut q = ShortQueue::< {QSIZE as usize} >::new();
+        let mut q = ShortQueue::<u8, {QSIZE as usize}>::new();
 
         q.push(0);
         q.pop();
@@ -239,7 +1023,7 @@ mod tests {
     fn drain() {
         const QSIZE:u8 = 250;
 
-        let mut q = ShortQueue::<{QSIZE as usize}>::new();
+        let mut q = ShortQueue::<u8, {QSIZE as usize}>::new();
 
         q.push(0);
         q.pop();
@@ -255,15 +1039,35 @@ mod tests {
         assert!( q.is_empty() );
     }
 
+    #[test]
+    fn max_size_256() {
+        // `N == 256` is the one size where `N as u8` truncates to `0`;
+        // make sure the modulo arithmetic doesn't divide by it.
+        let mut q = ShortQueue::<u8, 256>::new();
+
+        assert_eq!( q.capacity(), 255 );
+
+        for k in 0..255u8 {
+            assert_eq!( q.push( k ), true );
+        }
+        assert!( q.is_full() );
+        assert_eq!( q.push( 0 ), false );
+
+        for k in 0..255u8 {
+            assert_eq!( q.pop(), Some(k) );
+        }
+        assert!( q.is_empty() );
+    }
+
     #[test]
     fn static_new() {
-        static mut _Q: ShortQueue<5> = ShortQueue::new();
+        static mut _Q: ShortQueue<u8, 5> = ShortQueue::new();
     }
 
     #[test]
     fn split() {
         const QSIZE:u8 = 4;
-        let mut q = ShortQueue::<4>::new();
+        let mut q = ShortQueue::<u8, 4>::new();
 
         let (mut p, mut c) = q.split();
 
@@ -279,4 +1083,200 @@ mod tests {
             assert_eq!( c.pop(), Some(k) );
         }
     }
+
+    #[test]
+    fn push_slice_pop_slice() {
+        let mut q = ShortQueue::<u8, 8>::new();
+
+        assert_eq!( q.push_slice( &[1,2,3,4,5] ), 5 );
+        // Only 2 more bytes fit (capacity is 7).
+        assert_eq!( q.push_slice( &[6,7,8] ), 2 );
+
+        let mut dst = [0u8; 8];
+        assert_eq!( q.pop_slice( &mut dst ), 7 );
+        assert_eq!( &dst[..7], &[1,2,3,4,5,6,7] );
+        assert!( q.is_empty() );
+    }
+
+    #[test]
+    fn push_slice_pop_slice_wraps() {
+        let mut q = ShortQueue::<u8, 4>::new();
+
+        assert_eq!( q.push_slice( &[1,2,3] ), 3 );
+
+        let mut dst = [0u8; 2];
+        assert_eq!( q.pop_slice( &mut dst ), 2 );
+        assert_eq!( dst, [1,2] );
+
+        // `tail` is now near the end of `buf`; this push must wrap.
+        assert_eq!( q.push_slice( &[4,5,6] ), 2 );
+
+        let mut dst = [0u8; 3];
+        assert_eq!( q.pop_slice( &mut dst ), 3 );
+        assert_eq!( dst, [3,4,5] );
+    }
+
+    #[test]
+    fn drop_pops_remaining_elements() {
+        use core::cell::RefCell;
+
+        struct DropCounter<'a>( &'a RefCell<u32> );
+
+        impl<'a> Drop for DropCounter<'a> {
+            fn drop( &mut self ) {
+                *self.0.borrow_mut() += 1;
+            }
+        }
+
+        let count = RefCell::new( 0 );
+
+        {
+            let mut q = ShortQueue::<DropCounter<'_>, 4>::new();
+            q.push( DropCounter( &count ) );
+            q.push( DropCounter( &count ) );
+            q.pop();
+        }
+
+        // One element was popped (and dropped by the caller), one was
+        // left in the queue and must be dropped when `q` itself drops.
+        assert_eq!( *count.borrow(), 2 );
+    }
+
+    #[test]
+    fn padded_basics() {
+        assert!( core::mem::size_of::<CachePadded<AtomicU8>>() >= 64 );
+
+        let mut q = PaddedShortQueue::<u8, 8>::new();
+
+        for k in 0..7 {
+            assert_eq!( q.push( k ), true );
+        }
+        assert!( q.is_full() );
+        assert_eq!( q.push( 8 ), false );
+
+        for k in 0..7 {
+            assert_eq!( q.pop(), Some(k) );
+        }
+        assert!( q.is_empty() );
+    }
+
+    #[test]
+    fn padded_split() {
+        let mut q = PaddedShortQueue::<u8, 4>::new();
+        let (mut p, mut c) = q.split();
+
+        assert!( p.push(5) );
+        assert_eq!( c.pop(), Some( 5 ) );
+    }
+
+    #[test]
+    fn padded_push_slice_pop_slice_and_peek() {
+        let mut q = PaddedShortQueue::<u8, 8>::new();
+
+        assert_eq!( q.push_slice( &[1,2,3] ), 3 );
+        assert_eq!( q.peek(), Some(1) );
+
+        let mut dst = [0u8; 2];
+        assert_eq!( q.peek_slice( &mut dst ), 2 );
+        assert_eq!( dst, [1,2] );
+
+        let mut dst = [0u8; 3];
+        assert_eq!( q.pop_slice( &mut dst ), 3 );
+        assert_eq!( dst, [1,2,3] );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn producer_std_io_write() {
+        use std::io::Write;
+
+        let mut q = ShortQueue::<u8, 8>::new();
+        let (mut p, mut c) = q.split();
+
+        assert_eq!( p.write( &[1,2,3,4,5,6,7] ).unwrap(), 7 );
+        assert!( c.is_full() );
+
+        let mut dst = [0u8; 7];
+        assert_eq!( c.pop_slice( &mut dst ), 7 );
+        assert_eq!( dst, [1,2,3,4,5,6,7] );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn padded_producer_std_io_write() {
+        use std::io::Write;
+
+        let mut q = PaddedShortQueue::<u8, 8>::new();
+        let (mut p, mut c) = q.split();
+
+        assert_eq!( p.write( &[1,2,3,4,5,6,7] ).unwrap(), 7 );
+        assert!( c.is_full() );
+
+        let mut dst = [0u8; 7];
+        assert_eq!( c.pop_slice( &mut dst ), 7 );
+        assert_eq!( dst, [1,2,3,4,5,6,7] );
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn split_arc_basics() {
+        let q = ShortQueue::<u8, 4>::new();
+        let (mut p, mut c) = q.split_arc();
+
+        assert!( p.push(5) );
+        assert_eq!( c.pop(), Some( 5 ) );
+
+        assert!( c.is_empty() );
+        assert!( !c.is_full() );
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn split_arc_push_slice_pop_slice_and_peek() {
+        let q = ShortQueue::<u8, 8>::new();
+        let (mut p, mut c) = q.split_arc();
+
+        assert_eq!( p.push_slice( &[1,2,3] ), 3 );
+        assert_eq!( c.peek(), Some(1) );
+
+        let mut dst = [0u8; 3];
+        assert_eq!( c.pop_slice( &mut dst ), 3 );
+        assert_eq!( dst, [1,2,3] );
+    }
+
+    #[test]
+    fn peek_does_not_consume() {
+        let mut q = ShortQueue::<u8, 8>::new();
+
+        q.push_slice( &[1,2,3] );
+
+        assert_eq!( q.peek(), Some(1) );
+        assert_eq!( q.peek(), Some(1) );
+
+        let mut dst = [0u8; 2];
+        assert_eq!( q.peek_slice( &mut dst ), 2 );
+        assert_eq!( dst, [1,2] );
+
+        // Nothing above should have advanced `head`.
+        assert_eq!( q.pop(), Some(1) );
+        assert_eq!( q.pop(), Some(2) );
+        assert_eq!( q.pop(), Some(3) );
+        assert_eq!( q.peek(), None );
+    }
+
+    #[test]
+    fn single_core_basics() {
+        let mut q = ShortQueue::<u8, 8, SingleCore>::new();
+
+        for k in 0..7 {
+            assert_eq!( q.push( k ), true );
+        }
+        assert!( q.is_full() );
+        assert_eq!( q.push( 8 ), false );
+
+        for k in 0..7 {
+            assert_eq!( q.pop(), Some(k) );
+        }
+        assert!( q.is_empty() );
+    }
 }